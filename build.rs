@@ -0,0 +1,123 @@
+//! Generates the mnemonic parse table, the `Opcode` -> mnemonic decode helper, and the
+//! `Display` impl for `Opcode` from `instructions.in`, so for an opcode with a common operand
+//! shape those three `match`es come from one line in that file. The `Opcode` variant itself
+//! and its execution in `Node` are not generated and are still written in `src/main.rs`.
+//!
+//! Emits `parse_mnemonic.rs`, `opcode_mnemonic.rs`, and `opcode_display.rs` into `OUT_DIR`
+//! as complete items; each is pulled into `src/main.rs` via a top-level `include!`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=instructions.in");
+
+    let source = fs::read_to_string("instructions.in").expect("failed to read instructions.in");
+
+    let mut parse_arms = String::new();
+    let mut mnemonic_arms = String::new();
+    let mut display_arms = String::new();
+
+    for (line_no, raw_line) in source.lines().enumerate() {
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [mnemonic, shape, variant] = fields[..] else {
+            panic!("instructions.in:{}: expected `MNEMONIC SHAPE VARIANT`", line_no + 1);
+        };
+
+        parse_arms.push_str(&match shape {
+            "none" => format!("        \"{mnemonic}\" => Ok(Opcode::{variant}),\n"),
+            "value" => format!(
+                "        \"{mnemonic}\" => Ok(Opcode::{variant}(parse_value(line_no, operand(0)?)?)),\n"
+            ),
+            "value_port" => format!(
+                "        \"{mnemonic}\" => {{\n\
+                 \x20           let src = parse_value(line_no, operand(0)?)?;\n\
+                 \x20           let dst = parse_port(line_no, operand(1)?)?;\n\
+                 \x20           if matches!(&src, Value::Port(port) if port.is_directional()) && dst.is_directional() {{\n\
+                 \x20               return Err(ParseError::PassThroughMov(line_no));\n\
+                 \x20           }}\n\
+                 \x20           Ok(Opcode::{variant}(src, dst))\n\
+                 \x20       }}\n"
+            ),
+            "target" => format!(
+                "        \"{mnemonic}\" => Ok(Opcode::{variant}(parse_target(line_no, operand(0)?, labels)?)),\n"
+            ),
+            other => panic!("instructions.in:{}: unknown operand shape `{other}`", line_no + 1),
+        });
+
+        mnemonic_arms.push_str(&match shape {
+            "none" => format!("            Opcode::{variant} => \"{mnemonic}\",\n"),
+            "value" | "target" => format!("            Opcode::{variant}(_) => \"{mnemonic}\",\n"),
+            "value_port" => format!("            Opcode::{variant}(_, _) => \"{mnemonic}\",\n"),
+            other => panic!("instructions.in:{}: unknown operand shape `{other}`", line_no + 1),
+        });
+
+        display_arms.push_str(&match shape {
+            "none" => format!(
+                "            Opcode::{variant} => write!(f, \"{{}}\", self.mnemonic()),\n"
+            ),
+            "value" => format!(
+                "            Opcode::{variant}(val) => write!(f, \"{{}} {{}}\", self.mnemonic(), val),\n"
+            ),
+            "target" => format!(
+                "            Opcode::{variant}(target) => write!(f, \"{{}} {{}}\", self.mnemonic(), target),\n"
+            ),
+            "value_port" => format!(
+                "            Opcode::{variant}(val, dst) => write!(f, \"{{}} {{}}, {{:?}}\", self.mnemonic(), val, dst),\n"
+            ),
+            other => panic!("instructions.in:{}: unknown operand shape `{other}`", line_no + 1),
+        });
+    }
+
+    let parse_mnemonic = format!(
+        "/// Parse an upper-cased mnemonic and its operands into an [`Opcode`], generated from\n\
+         /// `instructions.in`\n\
+         fn parse_mnemonic<'a>(\n\
+         \x20   line_no: usize,\n\
+         \x20   mnemonic: &str,\n\
+         \x20   operand: &dyn Fn(usize) -> Result<&'a str, ParseError>,\n\
+         \x20   labels: &HashMap<String, usize>,\n\
+         ) -> Result<Opcode, ParseError> {{\n\
+         \x20   match mnemonic {{\n\
+         {parse_arms}\
+         \x20       other => Err(ParseError::UnknownMnemonic(line_no, other.to_string())),\n\
+         \x20   }}\n\
+         }}\n"
+    );
+
+    let opcode_mnemonic = format!(
+        "impl Opcode {{\n\
+         \x20   /// The mnemonic this opcode was parsed from / renders as, generated from\n\
+         \x20   /// `instructions.in`\n\
+         \x20   fn mnemonic(&self) -> &'static str {{\n\
+         \x20       match self {{\n\
+         {mnemonic_arms}\
+         \x20       }}\n\
+         \x20   }}\n\
+         }}\n"
+    );
+
+    let opcode_display = format!(
+        "impl Display for Opcode {{\n\
+         \x20   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{\n\
+         \x20       match self {{\n\
+         {display_arms}\
+         \x20       }}\n\
+         \x20   }}\n\
+         }}\n"
+    );
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
+    fs::write(Path::new(&out_dir).join("parse_mnemonic.rs"), parse_mnemonic)
+        .expect("write parse_mnemonic.rs");
+    fs::write(Path::new(&out_dir).join("opcode_mnemonic.rs"), opcode_mnemonic)
+        .expect("write opcode_mnemonic.rs");
+    fs::write(Path::new(&out_dir).join("opcode_display.rs"), opcode_display)
+        .expect("write opcode_display.rs");
+}
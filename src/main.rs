@@ -1,8 +1,9 @@
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Display;
 use std::ops::Neg;
 
 /// Communication channel between nodes in the CPU
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Port {
     /// Left communication channel from a node
     Left,
@@ -20,7 +21,21 @@ enum Port {
     Any,
 
     /// The last read or written used port by [`Port::Any`]
-    Last
+    Last,
+
+    /// The `ACC` register, usable as a `MOV` source or destination
+    Acc,
+
+    /// Discarded when written, zero when read
+    Nil
+}
+
+impl Port {
+    /// Whether this port requires communication with a neighboring [`Node`],
+    /// as opposed to [`Port::Acc`] / [`Port::Nil`] which only touch local state
+    fn is_directional(&self) -> bool {
+        matches!(self, Port::Left | Port::Right | Port::Up | Port::Down | Port::Any | Port::Last)
+    }
 }
 
 /// Operational unit used by the CPU nodes
@@ -53,7 +68,7 @@ enum Opcode {
     Add(Value),
 
     /// Subtract the [`Value`] from the `ACC` register and store the result back into the
-    /// `ACC` 
+    /// `ACC`
     Sub(Value),
 
     /// The values of `ACC` and `BAK` are exchanged
@@ -63,21 +78,81 @@ enum Opcode {
     Save,
 
     /// The value of `ACC` is arithmetically negated. Zero remains the same.
-    Negate
+    Negate,
+
+    /// Copy the [`Value`] into the destination [`Port`]
+    Mov(Value, Port),
+
+    /// Do nothing
+    Nop,
+
+    /// Jump to the instruction at this index unconditionally
+    Jmp(usize),
+
+    /// Jump to the instruction at this index if `ACC` is zero
+    Jez(usize),
+
+    /// Jump to the instruction at this index if `ACC` is non-zero
+    Jnz(usize),
+
+    /// Jump to the instruction at this index if `ACC` is greater than zero
+    Jgz(usize),
+
+    /// Jump to the instruction at this index if `ACC` is less than zero
+    Jlz(usize),
+
+    /// Jump relative to the current instruction by the offset in [`Value`], clamped into the
+    /// valid instruction range
+    Jro(Value)
 }
 
-impl Display for Opcode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Opcode::Add(val) => write!(f, "ADD {}", val),
-            Opcode::Sub(val) => write!(f, "SUB {}", val),
-            Opcode::Swap     => write!(f, "SWP"),
-            Opcode::Save     => write!(f, "SAV"),
-            Opcode::Negate   => write!(f, "NEG"),
-        }
-    }
+// `Opcode::mnemonic` and `impl Display for Opcode` are generated from `instructions.in` by
+// `build.rs`; an opcode whose operands fit one of the shapes it already knows needs its line
+// there instead of arms here. Its variant above and its execution in `Node` are not generated
+// and are written by hand.
+include!(concat!(env!("OUT_DIR"), "/opcode_mnemonic.rs"));
+include!(concat!(env!("OUT_DIR"), "/opcode_display.rs"));
+
+/// Execution state of a [`Node`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    /// Executing instructions normally
+    #[default]
+    Running,
+
+    /// Stalled on a port read because no neighbor has offered a matching value yet
+    BlockedOnRead,
+
+    /// Stalled on a port write because no neighbor has taken the value yet
+    BlockedOnWrite
 }
 
+/// The communication a [`Node`] wants to perform this cycle, computed before any node is
+/// allowed to actually mutate its registers or advance its `pc`
+#[derive(Debug)]
+enum PortRequest {
+    /// Read a value, trying each candidate [`Port`] in priority order
+    Read(Vec<Port>),
+
+    /// Write a value to a neighbor, trying each candidate [`Port`] in priority order
+    Write(Vec<Port>, i16)
+}
+
+/// What kind of node this is. A [`NodeKind::Compute`] node runs its `opcodes`; the input and
+/// output variants are puzzle I/O stand-ins that only ever offer or record a single stream of
+/// values, exchanged with whichever neighbor matches first in `ANY` order.
+#[derive(Debug, Default, Clone)]
+enum NodeKind {
+    /// Runs this node's `opcodes` program
+    #[default]
+    Compute,
+
+    /// Offers each value, in order, on any port a neighbor reads from until the queue is empty
+    Input(VecDeque<i16>),
+
+    /// Records every value a neighbor writes to this node on any port, in order
+    Output(Vec<i16>)
+}
 
 /// An individual node of execution in the CPU
 #[derive(Debug, Default)]
@@ -92,45 +167,250 @@ struct Node {
     opcodes: Vec<Opcode>,
 
     /// The current instruction being executed
-    pc: usize
+    pc: usize,
+
+    /// Whether this node is running or stalled on a port
+    status: Status,
+
+    /// The direction [`Port::Any`] most recently resolved to, used to answer [`Port::Last`]
+    last_direction: Option<Port>,
+
+    /// Whether this node runs a program or stands in for puzzle input/output
+    kind: NodeKind,
+
+    /// Labels defined in this node's program, mapping name to instruction index. Jump targets
+    /// are already resolved to raw indices inside [`Opcode`] by parse time; this is kept around
+    /// so [`Display for Cpu`] can show the name next to the instruction it points at.
+    labels: HashMap<String, usize>
 }
 
 impl Node {
-    pub fn step(&mut self) {
-        let curr_op = &self.opcodes[self.pc];
-
-        match curr_op {
-            Opcode::Add(val) | Opcode::Sub(val) => {
-                // Get the underlying value
-                let num = match val {
-                    Value::Number(num) => num,
-                    Value::Port(_port)  => unimplemented!()
-                };
-
-                // Perform the operation
-                match curr_op {
-                    Opcode::Add(_) => self.acc += num,
-                    Opcode::Sub(_) => self.acc -= num,
-                    _ => unreachable!()
+    /// Resolve a [`Value`] that does not require talking to a neighbor
+    fn resolve_local_value(&self, value: &Value) -> i16 {
+        match value {
+            Value::Number(num) => *num,
+            Value::Port(Port::Acc) => self.acc,
+            Value::Port(Port::Nil) => 0,
+            Value::Port(port) => unreachable!("{port:?} requires inter-node communication"),
+        }
+    }
+
+    /// Expand a [`Port`] into the directions to try, in priority order
+    fn candidates(&self, port: Port) -> Vec<Port> {
+        match port {
+            Port::Left | Port::Right | Port::Up | Port::Down => vec![port],
+            Port::Any => vec![Port::Left, Port::Right, Port::Up, Port::Down],
+            // No prior ANY/LAST has resolved a direction yet; block forever rather than guess,
+            // same as a directional port whose neighbor never answers
+            Port::Last => self.last_direction.into_iter().collect(),
+            Port::Acc | Port::Nil => unreachable!("{port:?} is not a directional port"),
+        }
+    }
+
+    /// Compute the communication this node needs this cycle, or `None` if the current
+    /// instruction only touches local state and can execute immediately
+    fn port_request(&self) -> Option<PortRequest> {
+        match &self.kind {
+            NodeKind::Input(queue) => {
+                queue.front().map(|&value| PortRequest::Write(self.candidates(Port::Any), value))
+            }
+            NodeKind::Output(_) => Some(PortRequest::Read(self.candidates(Port::Any))),
+            // A node left without a program for its `@N` block behaves as an infinite NOP
+            NodeKind::Compute if self.opcodes.is_empty() => None,
+            NodeKind::Compute => match &self.opcodes[self.pc] {
+                Opcode::Add(Value::Port(port)) | Opcode::Sub(Value::Port(port))
+                    if port.is_directional() =>
+                {
+                    Some(PortRequest::Read(self.candidates(*port)))
+                }
+                Opcode::Jro(Value::Port(port)) if port.is_directional() => {
+                    Some(PortRequest::Read(self.candidates(*port)))
                 }
+                Opcode::Mov(src, dst) => {
+                    if let Value::Port(port) = src {
+                        if port.is_directional() {
+                            return Some(PortRequest::Read(self.candidates(*port)));
+                        }
+                    }
+
+                    if dst.is_directional() {
+                        let value = self.resolve_local_value(src);
+                        return Some(PortRequest::Write(self.candidates(*dst), value));
+                    }
+
+                    None
+                }
+                _ => None,
+            },
+        }
+    }
 
-                // Clamp value to within bounds
-                self.acc = self.acc.clamp(-999, 999);
+    /// Execute the current instruction in place. Only called when [`Node::port_request`]
+    /// returned `None`. Returns whether an instruction actually ran, as opposed to this node
+    /// having nothing to do this cycle (an idle I/O node or an unprogrammed `@N` block).
+    fn exec_local(&mut self) -> bool {
+        if !matches!(self.kind, NodeKind::Compute) {
+            // An input node with an empty queue has nothing left to offer this cycle
+            return false;
+        }
+
+        if self.opcodes.is_empty() {
+            // A node left without a program for its `@N` block behaves as an infinite NOP
+            return false;
+        }
+
+        match &self.opcodes[self.pc] {
+            Opcode::Add(val) => {
+                let num = self.resolve_local_value(val);
+                self.acc = (self.acc + num).clamp(-999, 999);
+                self.advance_pc();
+            }
+            Opcode::Sub(val) => {
+                let num = self.resolve_local_value(val);
+                self.acc = (self.acc - num).clamp(-999, 999);
+                self.advance_pc();
+            }
+            Opcode::Swap => {
+                std::mem::swap(&mut self.acc, &mut self.bak);
+                self.advance_pc();
+            }
+            Opcode::Save => {
+                self.bak = self.acc;
+                self.advance_pc();
+            }
+            Opcode::Negate => {
+                self.acc = self.acc.neg();
+                self.advance_pc();
+            }
+            Opcode::Mov(src, dst) => {
+                let value = self.resolve_local_value(src);
+                match dst {
+                    Port::Acc => self.acc = value.clamp(-999, 999),
+                    Port::Nil => {}
+                    port => unreachable!("{port:?} is handled via PortRequest::Write"),
+                }
+                self.advance_pc();
+            }
+            Opcode::Nop => self.advance_pc(),
+            Opcode::Jmp(target) => self.jump_to(*target),
+            Opcode::Jez(target) => {
+                let condition = self.acc == 0;
+                self.jump_if(condition, *target);
+            }
+            Opcode::Jnz(target) => {
+                let condition = self.acc != 0;
+                self.jump_if(condition, *target);
+            }
+            Opcode::Jgz(target) => {
+                let condition = self.acc > 0;
+                self.jump_if(condition, *target);
+            }
+            Opcode::Jlz(target) => {
+                let condition = self.acc < 0;
+                self.jump_if(condition, *target);
+            }
+            Opcode::Jro(val) => {
+                let offset = self.resolve_local_value(val);
+                self.jump_relative(offset);
             }
-            Opcode::Swap => std::mem::swap(&mut self.acc, &mut self.bak),
-            Opcode::Save => self.bak = self.acc,
-            Opcode::Negate => self.acc = self.acc.neg()
         }
 
-        println!();
+        true
+    }
+
+    /// Jump directly to `target` if `condition` holds, otherwise fall through as usual
+    fn jump_if(&mut self, condition: bool, target: usize) {
+        if condition {
+            self.jump_to(target);
+        } else {
+            self.advance_pc();
+        }
+    }
+
+    /// Jump directly to `target`, clamping it into the valid instruction range
+    fn jump_to(&mut self, target: usize) {
+        self.pc = target.min(self.opcodes.len().saturating_sub(1));
+        self.status = Status::Running;
+    }
+
+    /// Jump relative to the current instruction by `offset`, clamping into the valid
+    /// instruction range rather than wrapping
+    fn jump_relative(&mut self, offset: i16) {
+        let target = self.pc as isize + offset as isize;
+        let max = self.opcodes.len().saturating_sub(1) as isize;
+        self.pc = target.clamp(0, max) as usize;
+        self.status = Status::Running;
+    }
+
+    /// A neighbor has taken the value this node offered on `dir`
+    fn complete_write(&mut self, dir: Port) {
+        if let NodeKind::Input(queue) = &mut self.kind {
+            queue.pop_front();
+            self.status = Status::Running;
+            return;
+        }
+
+        if let Opcode::Mov(_, Port::Any) = &self.opcodes[self.pc] {
+            self.last_direction = Some(dir);
+        }
+
+        self.advance_pc();
+    }
+
+    /// A neighbor has offered `value` on `dir`; apply it to the current instruction
+    fn complete_read(&mut self, value: i16, dir: Port) {
+        if let NodeKind::Output(values) = &mut self.kind {
+            values.push(value);
+            self.status = Status::Running;
+            return;
+        }
+
+        match &self.opcodes[self.pc] {
+            Opcode::Add(Value::Port(port)) => {
+                if *port == Port::Any {
+                    self.last_direction = Some(dir);
+                }
+                self.acc = (self.acc + value).clamp(-999, 999);
+                self.advance_pc();
+            }
+            Opcode::Sub(Value::Port(port)) => {
+                if *port == Port::Any {
+                    self.last_direction = Some(dir);
+                }
+                self.acc = (self.acc - value).clamp(-999, 999);
+                self.advance_pc();
+            }
+            Opcode::Mov(Value::Port(port), dst) => {
+                if *port == Port::Any {
+                    self.last_direction = Some(dir);
+                }
+                match dst {
+                    Port::Acc => self.acc = value.clamp(-999, 999),
+                    Port::Nil => {}
+                    port => unreachable!("{port:?} is handled via PortRequest::Write"),
+                }
+                self.advance_pc();
+            }
+            Opcode::Jro(Value::Port(port)) => {
+                if *port == Port::Any {
+                    self.last_direction = Some(dir);
+                }
+                self.jump_relative(value);
+            }
+            op => unreachable!("complete_read called for non-port-read opcode {op}"),
+        }
+    }
 
-        // Go to the next instruction
+    /// Advance to the next instruction, looping the program once at the end, and clear any
+    /// blocked status
+    fn advance_pc(&mut self) {
         self.pc += 1;
 
-        // Loop the program once at the end
         if self.pc >= self.opcodes.len() {
             self.pc = 0;
         }
+
+        self.status = Status::Running;
     }
 }
 
@@ -142,11 +422,135 @@ struct Cpu {
 }
 
 impl Cpu {
-    pub fn step(&mut self) {
-        for node in self.nodes.iter_mut() {
-            node.step();
+    /// Number of node columns in the grid. Must match the layout assumed by
+    /// [`Display for Cpu`].
+    const COLUMNS: usize = 2;
+
+    /// Number of node rows in the grid. Must match the layout assumed by [`Display for Cpu`].
+    const ROWS: usize = 2;
+
+    /// The node adjacent to `node_index` in direction `dir`, or `None` if `dir` points off
+    /// the edge of the grid
+    fn neighbor_index(node_index: usize, dir: Port) -> Option<usize> {
+        let row = node_index / Self::COLUMNS;
+        let col = node_index % Self::COLUMNS;
+
+        match dir {
+            Port::Left if col > 0 => Some(node_index - 1),
+            Port::Right if col + 1 < Self::COLUMNS => Some(node_index + 1),
+            Port::Up if row > 0 => Some(node_index - Self::COLUMNS),
+            Port::Down if row + 1 < Self::ROWS => Some(node_index + Self::COLUMNS),
+            _ => None,
         }
     }
+
+    /// The port a neighbor sees this communication arrive on
+    fn opposite(dir: Port) -> Port {
+        match dir {
+            Port::Left => Port::Right,
+            Port::Right => Port::Left,
+            Port::Up => Port::Down,
+            Port::Down => Port::Up,
+            port => unreachable!("{port:?} is not a directional port"),
+        }
+    }
+
+    /// Advance every node by one cycle. Returns the number of `Compute` nodes that completed
+    /// an instruction this cycle (port-blocked or idle I/O nodes don't count).
+    pub fn step(&mut self) -> usize {
+        // Phase one: every node computes the communication it wants this cycle without
+        // mutating anything yet
+        let requests: Vec<Option<PortRequest>> =
+            self.nodes.iter().map(Node::port_request).collect();
+
+        let mut matched = vec![false; self.nodes.len()];
+        let mut resolved_value: Vec<Option<i16>> = vec![None; self.nodes.len()];
+        let mut resolved_dir: Vec<Option<Port>> = vec![None; self.nodes.len()];
+
+        // Phase two: resolve matched reader/writer pairs. Nodes are visited in index order,
+        // so a lower-index node's ANY scan claims a match before a higher-index node's does.
+        for i in 0..self.nodes.len() {
+            if matched[i] {
+                continue;
+            }
+
+            match &requests[i] {
+                Some(PortRequest::Write(dirs, value)) => {
+                    for &dir in dirs {
+                        let Some(j) = Self::neighbor_index(i, dir) else { continue };
+                        if matched[j] {
+                            continue;
+                        }
+
+                        if let Some(PortRequest::Read(reader_dirs)) = &requests[j] {
+                            if reader_dirs.contains(&Self::opposite(dir)) {
+                                matched[i] = true;
+                                matched[j] = true;
+                                resolved_value[j] = Some(*value);
+                                resolved_dir[i] = Some(dir);
+                                resolved_dir[j] = Some(Self::opposite(dir));
+                                break;
+                            }
+                        }
+                    }
+                }
+                Some(PortRequest::Read(dirs)) => {
+                    for &dir in dirs {
+                        let Some(j) = Self::neighbor_index(i, dir) else { continue };
+                        if matched[j] {
+                            continue;
+                        }
+
+                        if let Some(PortRequest::Write(writer_dirs, value)) = &requests[j] {
+                            if writer_dirs.contains(&Self::opposite(dir)) {
+                                matched[i] = true;
+                                matched[j] = true;
+                                resolved_value[i] = Some(*value);
+                                resolved_dir[i] = Some(dir);
+                                resolved_dir[j] = Some(Self::opposite(dir));
+                                break;
+                            }
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let mut instructions = 0;
+
+        for (i, node) in self.nodes.iter_mut().enumerate() {
+            let is_compute = matches!(node.kind, NodeKind::Compute);
+
+            let executed = match &requests[i] {
+                None => node.exec_local(),
+                Some(PortRequest::Write(..)) => {
+                    if matched[i] {
+                        node.complete_write(resolved_dir[i].unwrap());
+                        true
+                    } else {
+                        node.status = Status::BlockedOnWrite;
+                        continue;
+                    }
+                }
+                Some(PortRequest::Read(..)) => {
+                    if matched[i] {
+                        node.complete_read(resolved_value[i].unwrap(), resolved_dir[i].unwrap());
+                        true
+                    } else {
+                        node.status = Status::BlockedOnRead;
+                        continue;
+                    }
+                }
+            };
+
+            if is_compute && executed {
+                instructions += 1;
+            }
+        }
+
+        instructions
+    }
 }
 
 impl Display for Cpu {
@@ -158,12 +562,12 @@ impl Display for Cpu {
         let node_padding = " ".repeat(6);
 
         // Number of nodes in each column
-        let columns = 2;
+        let columns = Self::COLUMNS;
 
-        for row in 0..2 {
+        for row in 0..Self::ROWS {
             // Print the header line
-            write!(f, "+{0:}+{1:}+{0:}+\n", 
-                "-".repeat(node_width), 
+            write!(f, "+{0:}+{1:}+{0:}+\n",
+                "-".repeat(node_width),
                 node_padding)?;
 
             for instr in 0..6 {
@@ -176,7 +580,7 @@ impl Display for Cpu {
                         // Get the node index for the current node
                         let node_index = row * columns + col;
 
-                        write!(f, 
+                        write!(f,
                             "{:width$}",
                             format!(" ACC: {:4} BAK: {:4}", self.nodes[node_index].acc,
                                 self.nodes[node_index].bak),
@@ -189,8 +593,8 @@ impl Display for Cpu {
                     write!(f, "\n")?;
 
                     // Print the barrier between registers and opcodes
-                    write!(f, "+{0:}+{1:}+{0:}+\n", 
-                        "-".repeat(node_width), 
+                    write!(f, "+{0:}+{1:}+{0:}+\n",
+                        "-".repeat(node_width),
                         node_padding)?;
                 }
 
@@ -202,16 +606,30 @@ impl Display for Cpu {
                     let node_index = row * columns + col;
 
                     if self.nodes[node_index].pc == instr {
-                        write!(f, "> ")?;
+                        let marker = match self.nodes[node_index].status {
+                            Status::Running => "> ",
+                            Status::BlockedOnRead => "R ",
+                            Status::BlockedOnWrite => "W ",
+                        };
+                        write!(f, "{}", marker)?;
                     } else {
                         write!(f, "  ")?;
                     }
 
                     match self.nodes[node_index].opcodes.get(instr) {
                         Some(opcode) => {
-                            let _ = write!(f, "{:width$}", 
-                                format!("{}", opcode), 
-                                width = node_width - 2);
+                            // Show the label pointing at this instruction, if this node defines
+                            // one, the same way the source named it
+                            let label = self.nodes[node_index].labels.iter()
+                                .find(|&(_, &target)| target == instr)
+                                .map(|(name, _)| name.as_str());
+
+                            let text = match label {
+                                Some(name) => format!("{name}: {opcode}"),
+                                None => format!("{opcode}"),
+                            };
+
+                            let _ = write!(f, "{:width$}", text, width = node_width - 2);
                         }
                         None => {
                             let _ = write!(f, "{:width$}", " ", width = node_width - 2);
@@ -225,8 +643,8 @@ impl Display for Cpu {
             }
 
             // Print the header line
-            write!(f, "+{0:}+{1:}+{0:}+\n", 
-                "-".repeat(node_width), 
+            write!(f, "+{0:}+{1:}+{0:}+\n",
+                "-".repeat(node_width),
                 node_padding)?;
 
             // Spacing between nodes
@@ -237,40 +655,935 @@ impl Display for Cpu {
     }
 }
 
+/// Error produced while parsing TIS-100 assembly
+#[derive(Debug)]
+enum ParseError {
+    /// Line `usize` used a mnemonic this assembler does not recognize
+    UnknownMnemonic(usize, String),
+
+    /// Line `usize` is missing an operand its mnemonic requires
+    MissingOperand(usize),
+
+    /// Line `usize` has an operand that isn't a valid number or port name
+    InvalidOperand(usize, String),
+
+    /// Line `usize` appeared before any `@N` header selected a target node
+    NoActiveNode(usize),
+
+    /// The `@N` header on line `usize` named a node index outside the grid
+    InvalidNodeIndex(usize, String),
+
+    /// Line `usize` referenced a label that is never defined in that node's program
+    UndefinedLabel(usize, String),
+
+    /// Line `usize` is a `MOV` with a directional port on both sides, which would need to
+    /// block on a read and a write in the same instruction; route the value through `ACC`
+    /// instead, e.g. `MOV LEFT, ACC` followed by `MOV ACC, RIGHT`
+    ///
+    /// This is a limitation of the two-phase scheduler in [`Cpu::step`] (each node issues at
+    /// most one [`PortRequest`] per cycle), not a puzzle-I/O restriction, so it is rejected at
+    /// parse time for every node rather than only ones wired up as input/output. Real TIS-100
+    /// allows a direct port-to-port `MOV`; this assembler does not.
+    PassThroughMov(usize),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnknownMnemonic(line, tok) =>
+                write!(f, "line {line}: unknown mnemonic `{tok}`"),
+            ParseError::MissingOperand(line) =>
+                write!(f, "line {line}: missing operand"),
+            ParseError::InvalidOperand(line, tok) =>
+                write!(f, "line {line}: invalid operand `{tok}`"),
+            ParseError::NoActiveNode(line) =>
+                write!(f, "line {line}: instruction given before any `@N` node header"),
+            ParseError::InvalidNodeIndex(line, tok) =>
+                write!(f, "line {line}: invalid node index `@{tok}`"),
+            ParseError::UndefinedLabel(line, tok) =>
+                write!(f, "line {line}: undefined label `{tok}`"),
+            ParseError::PassThroughMov(line) =>
+                write!(f, "line {line}: MOV cannot read and write a port in the same \
+                    instruction; route through ACC instead"),
+        }
+    }
+}
+
+/// A single node's program, as produced by [`parse_program`]
+#[derive(Debug)]
+struct NodeProgram {
+    /// The parsed instructions
+    opcodes: Vec<Opcode>,
+
+    /// Labels resolved while parsing, mapping name to instruction index
+    labels: HashMap<String, usize>
+}
+
+/// Parse TIS-100 assembly into a program for each [`Node`] in the grid.
+///
+/// The source is split into blocks with an `@N` header selecting the node the following
+/// instructions belong to, e.g.:
+///
+/// ```text
+/// @0
+/// ADD 1
+/// SAV
+///
+/// @1
+/// LOOP: MOV LEFT, ACC
+/// MOV ACC, DOWN
+/// JMP LOOP
+/// ```
+///
+/// Mnemonics are case-insensitive and `#` starts a line comment. A line of the form
+/// `NAME: INSTRUCTION` (or `NAME:` on its own) defines `NAME` as a label for the following
+/// instruction, which `JMP`/`JEZ`/`JNZ`/`JGZ`/`JLZ` can then target by name.
+fn parse_program(source: &str) -> Result<[NodeProgram; 4], ParseError> {
+    let mut node_lines: [Vec<(usize, String)>; 4] = Default::default();
+    let mut current: Option<usize> = None;
+
+    for (line_no, raw_line) in source.lines().enumerate() {
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix('@') {
+            let rest = rest.trim();
+            let index: usize = rest.parse()
+                .map_err(|_| ParseError::InvalidNodeIndex(line_no, rest.to_string()))?;
+
+            if index >= node_lines.len() {
+                return Err(ParseError::InvalidNodeIndex(line_no, rest.to_string()));
+            }
+
+            current = Some(index);
+            continue;
+        }
+
+        let node_index = current.ok_or(ParseError::NoActiveNode(line_no))?;
+        node_lines[node_index].push((line_no, line.to_string()));
+    }
+
+    let mut programs: [Option<NodeProgram>; 4] = Default::default();
+    for (node_index, lines) in node_lines.iter().enumerate() {
+        programs[node_index] = Some(parse_node_program(lines)?);
+    }
+
+    Ok(programs.map(|program| program.expect("every node index was parsed above")))
+}
+
+/// Parse one node's lines, resolving any labels they define
+fn parse_node_program(lines: &[(usize, String)]) -> Result<NodeProgram, ParseError> {
+    // First pass: strip label definitions, recording the instruction index each one resolves to
+    let mut labels = HashMap::new();
+    let mut instructions: Vec<(usize, String)> = Vec::new();
+
+    for (line_no, line) in lines {
+        let mut text = line.as_str();
+
+        if let Some(colon) = text.find(':') {
+            let (name, rest) = text.split_at(colon);
+            let name = name.trim();
+
+            if is_label_name(name) {
+                labels.insert(name.to_string(), instructions.len());
+                text = rest[1..].trim();
+
+                if text.is_empty() {
+                    continue;
+                }
+            }
+        }
+
+        instructions.push((*line_no, text.to_string()));
+    }
+
+    // Second pass: parse each instruction now that every label in this node is known
+    let opcodes = instructions.iter()
+        .map(|(line_no, text)| parse_instruction(*line_no, text, &labels))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(NodeProgram { opcodes, labels })
+}
+
+/// Whether `name` is a valid label identifier
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse a single non-empty, non-header, non-comment, label-stripped line into an [`Opcode`]
+fn parse_instruction(
+    line_no: usize,
+    line: &str,
+    labels: &HashMap<String, usize>,
+) -> Result<Opcode, ParseError> {
+    let tokens: Vec<&str> = line
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|tok| !tok.is_empty())
+        .collect();
+
+    let (mnemonic, operands) = tokens.split_first()
+        .ok_or(ParseError::MissingOperand(line_no))?;
+
+    let operand = |idx: usize| -> Result<&str, ParseError> {
+        operands.get(idx).copied().ok_or(ParseError::MissingOperand(line_no))
+    };
+
+    parse_mnemonic(line_no, &mnemonic.to_ascii_uppercase(), &operand, labels)
+}
+
+// Generated from instructions.in by build.rs; add an opcode there rather than here unless
+// its operands don't fit one of the shapes it already knows.
+include!(concat!(env!("OUT_DIR"), "/parse_mnemonic.rs"));
+
+/// Parse a jump operand as either a label name or a literal instruction index
+fn parse_target(
+    line_no: usize,
+    tok: &str,
+    labels: &HashMap<String, usize>,
+) -> Result<usize, ParseError> {
+    if let Ok(index) = tok.parse::<usize>() {
+        return Ok(index);
+    }
+
+    labels.get(tok)
+        .copied()
+        .ok_or_else(|| ParseError::UndefinedLabel(line_no, tok.to_string()))
+}
+
+/// Parse an operand as either a numeric literal or a [`Port`] name
+fn parse_value(line_no: usize, tok: &str) -> Result<Value, ParseError> {
+    if let Ok(num) = tok.parse::<i16>() {
+        return Ok(Value::Number(num));
+    }
+
+    parse_port(line_no, tok).map(Value::Port)
+}
+
+/// Parse an operand as a [`Port`] name
+fn parse_port(line_no: usize, tok: &str) -> Result<Port, ParseError> {
+    match tok.to_ascii_uppercase().as_str() {
+        "LEFT"  => Ok(Port::Left),
+        "RIGHT" => Ok(Port::Right),
+        "UP"    => Ok(Port::Up),
+        "DOWN"  => Ok(Port::Down),
+        "ANY"   => Ok(Port::Any),
+        "LAST"  => Ok(Port::Last),
+        "ACC"   => Ok(Port::Acc),
+        "NIL"   => Ok(Port::Nil),
+        _       => Err(ParseError::InvalidOperand(line_no, tok.to_string())),
+    }
+}
+
+/// The program run when no other source is given
+const DEFAULT_PROGRAM: &str = "\
+@0
+ADD 1
+SAV
+ADD 1
+SWP
+NEG
+
+@1
+ADD 2
+SUB 400
+
+@2
+ADD -400
+
+@3
+ADD 1
+SUB 2
+SUB 4
+SUB 5
+";
+
+/// Error produced while decoding a [`CpuSnapshot`] from bytes
+#[derive(Debug)]
+enum SnapshotError {
+    /// The byte buffer ended before a value it contains was fully read
+    Truncated,
+
+    /// A status byte did not match any [`Status`] variant
+    InvalidStatus(u8),
+
+    /// A direction byte did not match any directional [`Port`] or the absence of one
+    InvalidDirection(u8),
+
+    /// A node-kind tag byte did not match [`NodeKind::Compute`]/`Input`/`Output`
+    InvalidKind(u8),
+
+    /// The buffer had bytes left over after every node was decoded
+    TrailingData
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Truncated => write!(f, "snapshot data ended early"),
+            SnapshotError::InvalidStatus(byte) => write!(f, "invalid status byte {byte}"),
+            SnapshotError::InvalidDirection(byte) => write!(f, "invalid direction byte {byte}"),
+            SnapshotError::InvalidKind(byte) => write!(f, "invalid node kind byte {byte}"),
+            SnapshotError::TrailingData => write!(f, "snapshot data has unexpected trailing bytes"),
+        }
+    }
+}
+
+/// A cursor over a byte slice used to decode a [`CpuSnapshot`]
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        let end = self.pos.checked_add(len).ok_or(SnapshotError::Truncated)?;
+        let slice = self.bytes.get(self.pos..end).ok_or(SnapshotError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, SnapshotError> {
+        let bytes = self.take(2)?;
+        Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+}
+
+impl NodeKind {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        match self {
+            NodeKind::Compute => out.push(0),
+            NodeKind::Input(queue) => {
+                out.push(1);
+                out.extend_from_slice(&(queue.len() as u32).to_le_bytes());
+                queue.iter().for_each(|value| out.extend_from_slice(&value.to_le_bytes()));
+            }
+            NodeKind::Output(values) => {
+                out.push(2);
+                out.extend_from_slice(&(values.len() as u32).to_le_bytes());
+                values.iter().for_each(|value| out.extend_from_slice(&value.to_le_bytes()));
+            }
+        }
+    }
+
+    fn read_bytes(reader: &mut ByteReader) -> Result<Self, SnapshotError> {
+        match reader.u8()? {
+            0 => Ok(NodeKind::Compute),
+            1 => {
+                let len = reader.u32()? as usize;
+                (0..len).map(|_| reader.i16()).collect::<Result<_, _>>().map(NodeKind::Input)
+            }
+            2 => {
+                let len = reader.u32()? as usize;
+                (0..len).map(|_| reader.i16()).collect::<Result<_, _>>().map(NodeKind::Output)
+            }
+            other => Err(SnapshotError::InvalidKind(other)),
+        }
+    }
+}
+
+/// A point-in-time capture of one [`Node`]'s registers and execution state
+#[derive(Debug, Clone)]
+struct NodeSnapshot {
+    /// The `ACC` register
+    acc: i16,
+
+    /// The `BAK` register
+    bak: i16,
+
+    /// The current instruction index
+    pc: usize,
+
+    /// Whether the node was running or stalled on a port
+    status: Status,
+
+    /// The direction [`Port::Any`] most recently resolved to
+    last_direction: Option<Port>,
+
+    /// The node's kind, including any in-flight input queue or recorded output
+    kind: NodeKind
+}
+
+impl NodeSnapshot {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.acc.to_le_bytes());
+        out.extend_from_slice(&self.bak.to_le_bytes());
+        out.extend_from_slice(&(self.pc as u32).to_le_bytes());
+
+        out.push(match self.status {
+            Status::Running => 0,
+            Status::BlockedOnRead => 1,
+            Status::BlockedOnWrite => 2,
+        });
+
+        out.push(match self.last_direction {
+            None => 0,
+            Some(Port::Left) => 1,
+            Some(Port::Right) => 2,
+            Some(Port::Up) => 3,
+            Some(Port::Down) => 4,
+            Some(port) => unreachable!("{port:?} cannot be the last resolved direction"),
+        });
+
+        self.kind.write_bytes(out);
+    }
+
+    fn read_bytes(reader: &mut ByteReader) -> Result<Self, SnapshotError> {
+        let acc = reader.i16()?;
+        let bak = reader.i16()?;
+        let pc = reader.u32()? as usize;
+
+        let status = match reader.u8()? {
+            0 => Status::Running,
+            1 => Status::BlockedOnRead,
+            2 => Status::BlockedOnWrite,
+            other => return Err(SnapshotError::InvalidStatus(other)),
+        };
+
+        let last_direction = match reader.u8()? {
+            0 => None,
+            1 => Some(Port::Left),
+            2 => Some(Port::Right),
+            3 => Some(Port::Up),
+            4 => Some(Port::Down),
+            other => return Err(SnapshotError::InvalidDirection(other)),
+        };
+
+        let kind = NodeKind::read_bytes(reader)?;
+
+        Ok(NodeSnapshot { acc, bak, pc, status, last_direction, kind })
+    }
+}
+
+/// A point-in-time capture of the entire [`Cpu`]'s register and execution state. Programs and
+/// resolved labels are static for the lifetime of a run, so only what changes while stepping
+/// is captured.
+#[derive(Debug, Clone)]
+struct CpuSnapshot {
+    nodes: [NodeSnapshot; 4]
+}
+
+impl CpuSnapshot {
+    /// Capture the current state of `cpu`
+    fn capture(cpu: &Cpu) -> Self {
+        let nodes = std::array::from_fn(|i| {
+            let node = &cpu.nodes[i];
+            NodeSnapshot {
+                acc: node.acc,
+                bak: node.bak,
+                pc: node.pc,
+                status: node.status,
+                last_direction: node.last_direction,
+                kind: node.kind.clone(),
+            }
+        });
+
+        CpuSnapshot { nodes }
+    }
+
+    /// Overwrite `cpu`'s register and execution state with this snapshot
+    fn restore(&self, cpu: &mut Cpu) {
+        for (node, snapshot) in cpu.nodes.iter_mut().zip(&self.nodes) {
+            node.acc = snapshot.acc;
+            node.bak = snapshot.bak;
+            node.pc = snapshot.pc;
+            node.status = snapshot.status;
+            node.last_direction = snapshot.last_direction;
+            node.kind = snapshot.kind.clone();
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for node in &self.nodes {
+            node.write_bytes(&mut bytes);
+        }
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = ByteReader::new(bytes);
+
+        let mut nodes = Vec::with_capacity(4);
+        for _ in 0..4 {
+            nodes.push(NodeSnapshot::read_bytes(&mut reader)?);
+        }
+
+        if !reader.at_end() {
+            return Err(SnapshotError::TrailingData);
+        }
+
+        Ok(CpuSnapshot {
+            nodes: nodes.try_into()
+                .unwrap_or_else(|_| unreachable!("exactly 4 nodes were just read")),
+        })
+    }
+
+    /// Dump this snapshot to disk so it can be reloaded in a later session
+    fn save_to_file(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    /// Load a snapshot previously written with [`CpuSnapshot::save_to_file`]
+    fn load_from_file(path: &str) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+    }
+}
+
+/// A ring buffer of [`CpuSnapshot`]s captured before each [`Cpu::step`], letting the debugger
+/// step backward through execution
+struct History {
+    snapshots: VecDeque<CpuSnapshot>,
+    capacity: usize
+}
+
+impl History {
+    fn new(capacity: usize) -> Self {
+        History { snapshots: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    /// Record a snapshot, discarding the oldest one once `capacity` is exceeded
+    fn push(&mut self, snapshot: CpuSnapshot) {
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Pop the most recently recorded snapshot, if any
+    fn pop(&mut self) -> Option<CpuSnapshot> {
+        self.snapshots.pop_back()
+    }
+}
+
+/// Number of snapshots kept for stepping backward
+const HISTORY_CAPACITY: usize = 1000;
+
+/// Path a snapshot is dumped to / reloaded from between sessions
+const SNAPSHOT_FILE: &str = "tis100.snapshot";
+
+/// Result of running a [`Puzzle`] against a [`Cpu`]: the same metrics the game scores runs on
+struct PuzzleReport {
+    /// Whether every output stream matched its expected values exactly
+    passed: bool,
+
+    /// Number of cycles [`Cpu::step`] was called before the puzzle finished or timed out
+    cycles: usize,
+
+    /// Total number of `Compute` node instructions executed across the whole run
+    instructions: usize
+}
+
+impl Display for PuzzleReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in {} cycles ({} instructions executed)",
+            if self.passed { "PASS" } else { "FAIL" },
+            self.cycles,
+            self.instructions)
+    }
+}
+
+/// A TIS-100-style puzzle: which nodes feed in known input streams, and which nodes must
+/// produce the matching expected output streams
+struct Puzzle {
+    /// Node index -> values that node offers to its neighbors, in order, as an input node
+    inputs: HashMap<usize, Vec<i16>>,
+
+    /// Node index -> values that node must receive from its neighbors, in order, as an
+    /// output node, to pass
+    expected_outputs: HashMap<usize, Vec<i16>>
+}
+
+impl Puzzle {
+    fn new(inputs: HashMap<usize, Vec<i16>>, expected_outputs: HashMap<usize, Vec<i16>>) -> Self {
+        Puzzle { inputs, expected_outputs }
+    }
+
+    /// Load `cpu`'s I/O nodes with this puzzle's streams, then step it until every output
+    /// stream reaches its expected length or `max_cycles` is exhausted
+    fn run(&self, cpu: &mut Cpu, max_cycles: usize) -> PuzzleReport {
+        for (&index, values) in &self.inputs {
+            cpu.nodes[index].kind = NodeKind::Input(values.iter().copied().collect());
+        }
+
+        for &index in self.expected_outputs.keys() {
+            cpu.nodes[index].kind = NodeKind::Output(Vec::new());
+        }
+
+        let mut cycles = 0;
+        let mut instructions = 0;
+
+        while cycles < max_cycles && !self.outputs_complete(cpu) {
+            instructions += cpu.step();
+            cycles += 1;
+        }
+
+        PuzzleReport { passed: self.outputs_match(cpu), cycles, instructions }
+    }
+
+    /// Whether every expected output node has recorded at least as many values as expected
+    fn outputs_complete(&self, cpu: &Cpu) -> bool {
+        self.expected_outputs.iter().all(|(&index, expected)| {
+            matches!(&cpu.nodes[index].kind, NodeKind::Output(values) if values.len() >= expected.len())
+        })
+    }
+
+    /// Whether every expected output node's recorded values match exactly
+    fn outputs_match(&self, cpu: &Cpu) -> bool {
+        self.expected_outputs.iter().all(|(&index, expected)| {
+            matches!(&cpu.nodes[index].kind, NodeKind::Output(values) if values == expected)
+        })
+    }
+}
+
+/// A program that forwards values from node 0 (fed as puzzle input) to node 3 (read as puzzle
+/// output), used by `puzzle` mode to exercise [`Puzzle::run`]
+const PUZZLE_PROGRAM: &str = "\
+@1
+MOV LEFT, ACC
+MOV ACC, DOWN
+
+@2
+NOP
+";
+
+/// Run a small built-in puzzle and print the pass/fail report
+fn run_puzzle_demo() {
+    let mut cpu = Cpu::default();
+
+    let programs = parse_program(PUZZLE_PROGRAM).expect("puzzle program failed to parse");
+    for (node, program) in cpu.nodes.iter_mut().zip(programs) {
+        node.opcodes = program.opcodes;
+        node.labels = program.labels;
+    }
+
+    let inputs = HashMap::from([(0, vec![1, 2, 3, 4, 5])]);
+    let expected_outputs = HashMap::from([(3, vec![1, 2, 3, 4, 5])]);
+
+    let report = Puzzle::new(inputs, expected_outputs).run(&mut cpu, 1000);
+    println!("{report}");
+}
+
 fn main() -> std::io::Result<()> {
-    // Create the default CPU
-    let mut cpu  = Cpu::default();
+    let arg = std::env::args().nth(1);
 
-    // Fakes test nodes
-    cpu.nodes[0].opcodes.push(Opcode::Add(Value::Number(1)));
-    cpu.nodes[0].opcodes.push(Opcode::Save);
-    cpu.nodes[0].opcodes.push(Opcode::Add(Value::Number(1)));
-    cpu.nodes[0].opcodes.push(Opcode::Swap);
-    cpu.nodes[0].opcodes.push(Opcode::Negate);
+    if arg.as_deref() == Some("puzzle") {
+        run_puzzle_demo();
+        return Ok(());
+    }
 
-    cpu.nodes[1].opcodes.push(Opcode::Add(Value::Number(2)));
-    cpu.nodes[1].opcodes.push(Opcode::Sub(Value::Number(400)));
+    // Create the default CPU
+    let mut cpu  = Cpu::default();
 
-    cpu.nodes[2].opcodes.push(Opcode::Add(Value::Number(-400)));
+    let source = match &arg {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => DEFAULT_PROGRAM.to_string(),
+    };
+    let programs = parse_program(&source).unwrap_or_else(|err| {
+        eprintln!("{}: {err}", arg.as_deref().unwrap_or("<default program>"));
+        std::process::exit(1);
+    });
+    for (node, program) in cpu.nodes.iter_mut().zip(programs) {
+        node.opcodes = program.opcodes;
+        node.labels = program.labels;
+    }
 
-    cpu.nodes[3].opcodes.push(Opcode::Add(Value::Number(1)));
-    cpu.nodes[3].opcodes.push(Opcode::Sub(Value::Number(2)));
-    cpu.nodes[3].opcodes.push(Opcode::Sub(Value::Number(4)));
-    cpu.nodes[3].opcodes.push(Opcode::Sub(Value::Number(5)));
+    let mut history = History::new(HISTORY_CAPACITY);
 
-    // Init destination string for read_line 
+    // Init destination string for read_line
     let mut input = String::new();
 
-    // "Debugger" loop.. Waits for enter to step to the next iteration
+    // "Debugger" loop.. Waits for enter to step forward, `b` to step backward, `s`/`l` to
+    // save/load a snapshot to/from disk, `q` to quit
     loop {
         println!("{}", cpu);
         std::io::stdin().read_line(&mut input)?;
-        cpu.step();
+        let command = input.to_ascii_lowercase();
+        input.clear();
 
-        if input.to_ascii_lowercase().contains('q') {
+        if command.contains('q') {
             break;
+        } else if command.contains('b') {
+            if let Some(snapshot) = history.pop() {
+                snapshot.restore(&mut cpu);
+            }
+        } else if command.contains('s') {
+            CpuSnapshot::capture(&cpu).save_to_file(SNAPSHOT_FILE)?;
+        } else if command.contains('l') {
+            match CpuSnapshot::load_from_file(SNAPSHOT_FILE) {
+                Ok(snapshot) => snapshot.restore(&mut cpu),
+                Err(err) => println!("could not load {SNAPSHOT_FILE}: {err}"),
+            }
+        } else {
+            history.push(CpuSnapshot::capture(&cpu));
+            cpu.step();
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_program_rejects_node_index_out_of_range() {
+        let err = parse_program("@4\nNOP\n").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidNodeIndex(0, tok) if tok == "4"));
+    }
+
+    #[test]
+    fn parse_program_rejects_undefined_label() {
+        let err = parse_program("@0\nJMP NOWHERE\n").unwrap_err();
+        assert!(matches!(err, ParseError::UndefinedLabel(1, tok) if tok == "NOWHERE"));
+    }
+
+    #[test]
+    fn parse_program_rejects_pass_through_mov() {
+        let err = parse_program("@0\nMOV LEFT, RIGHT\n").unwrap_err();
+        assert!(matches!(err, ParseError::PassThroughMov(1)));
+    }
+
+    #[test]
+    fn parse_program_accepts_an_empty_at_block() {
+        let programs = parse_program("@0\nNOP\n").unwrap();
+        assert!(programs[1].opcodes.is_empty());
+    }
+
+    #[test]
+    fn compute_node_with_no_program_behaves_as_a_nop() {
+        let mut cpu = Cpu::default();
+        // Every node defaults to `NodeKind::Compute` with no opcodes loaded
+        assert_eq!(cpu.step(), 0);
+        assert_eq!(cpu.nodes[0].pc, 0);
+        assert_eq!(cpu.nodes[0].status, Status::Running);
+    }
+
+    #[test]
+    fn unprogrammed_neighbors_are_not_counted_alongside_a_running_node() {
+        let mut cpu = Cpu::default();
+        // Only node 0 has a program; nodes 1-3 are left as unprogrammed `@N` blocks, which
+        // behave as an infinite NOP and must not inflate the instruction count
+        cpu.nodes[0].opcodes = vec![Opcode::Nop];
+
+        assert_eq!(cpu.step(), 1);
+        assert_eq!(cpu.step(), 1);
+    }
+
+    #[test]
+    fn scheduler_matches_a_write_with_its_neighbors_read() {
+        let mut cpu = Cpu::default();
+        // Nodes 0 and 1 sit side by side in the grid, so node 0's RIGHT write should land on
+        // node 1's LEFT read in the same cycle
+        cpu.nodes[0].opcodes = vec![Opcode::Mov(Value::Number(5), Port::Right)];
+        cpu.nodes[1].opcodes = vec![Opcode::Mov(Value::Port(Port::Left), Port::Acc)];
+
+        let completed = cpu.step();
+
+        assert_eq!(completed, 2);
+        assert_eq!(cpu.nodes[1].acc, 5);
+    }
+
+    #[test]
+    fn scheduler_blocks_a_write_with_no_matching_read() {
+        let mut cpu = Cpu::default();
+        cpu.nodes[0].opcodes = vec![Opcode::Mov(Value::Number(5), Port::Right)];
+
+        cpu.step();
+
+        assert_eq!(cpu.nodes[0].status, Status::BlockedOnWrite);
+        assert_eq!(cpu.nodes[0].pc, 0);
+    }
+
+    #[test]
+    fn last_before_any_has_resolved_a_direction_blocks_forever_instead_of_panicking() {
+        let mut cpu = Cpu::default();
+        cpu.nodes[0].opcodes = vec![Opcode::Add(Value::Port(Port::Last))];
+
+        cpu.step();
+
+        assert_eq!(cpu.nodes[0].status, Status::BlockedOnRead);
+    }
+
+    #[test]
+    fn conditional_jump_branches_on_acc_and_falls_through_otherwise() {
+        let mut cpu = Cpu::default();
+        cpu.nodes[0].opcodes = vec![Opcode::Jnz(2), Opcode::Nop, Opcode::Nop];
+
+        cpu.nodes[0].acc = 1;
+        cpu.step();
+        assert_eq!(cpu.nodes[0].pc, 2);
+
+        cpu.nodes[0].pc = 0;
+        cpu.nodes[0].acc = 0;
+        cpu.step();
+        assert_eq!(cpu.nodes[0].pc, 1);
+    }
+
+    #[test]
+    fn jro_clamps_past_either_end_of_the_program_instead_of_wrapping() {
+        let mut cpu = Cpu::default();
+        cpu.nodes[0].opcodes =
+            vec![Opcode::Jro(Value::Number(0)), Opcode::Nop, Opcode::Nop];
+
+        cpu.nodes[0].opcodes[0] = Opcode::Jro(Value::Number(-5));
+        cpu.step();
+        assert_eq!(cpu.nodes[0].pc, 0);
+
+        cpu.nodes[0].pc = 0;
+        cpu.nodes[0].opcodes[0] = Opcode::Jro(Value::Number(5));
+        cpu.step();
+        assert_eq!(cpu.nodes[0].pc, 2);
+    }
+
+    #[test]
+    fn jro_0_loops_on_itself_forever() {
+        let mut cpu = Cpu::default();
+        cpu.nodes[0].opcodes = vec![Opcode::Jro(Value::Number(0))];
+
+        for _ in 0..3 {
+            cpu.step();
+            assert_eq!(cpu.nodes[0].pc, 0);
+        }
+    }
+
+    #[test]
+    fn display_shows_the_label_pointing_at_an_instruction() {
+        let programs = parse_program("@0\nLOOP: NOP\nJMP LOOP\n").unwrap();
+        let mut cpu = Cpu::default();
+        for (node, program) in cpu.nodes.iter_mut().zip(programs) {
+            node.opcodes = program.opcodes;
+            node.labels = program.labels;
+        }
+
+        assert!(format!("{cpu}").contains("LOOP: NOP"));
+    }
+
+    #[test]
+    fn generated_table_parses_and_displays_a_jump_mnemonic() {
+        let programs = parse_program("@0\nJNZ 0\n").unwrap();
+        assert!(matches!(programs[0].opcodes[..], [Opcode::Jnz(0)]));
+        assert_eq!(format!("{}", programs[0].opcodes[0]), "JNZ 0");
+    }
+
+    #[test]
+    fn cpu_snapshot_round_trips_through_bytes() {
+        let mut cpu = Cpu::default();
+        cpu.nodes[0].opcodes = vec![Opcode::Mov(Value::Number(5), Port::Right)];
+        cpu.step();
+        cpu.nodes[2].kind = NodeKind::Output(vec![1, 2, 3]);
+
+        let snapshot = CpuSnapshot::capture(&cpu);
+        let restored = CpuSnapshot::from_bytes(&snapshot.to_bytes()).unwrap();
+
+        restored.restore(&mut cpu);
+
+        assert_eq!(cpu.nodes[0].status, Status::BlockedOnWrite);
+        assert!(matches!(&cpu.nodes[2].kind, NodeKind::Output(values) if values == &[1, 2, 3]));
+    }
+
+    #[test]
+    fn cpu_snapshot_from_bytes_rejects_trailing_data() {
+        let mut bytes = CpuSnapshot::capture(&Cpu::default()).to_bytes();
+        bytes.push(0);
+
+        assert!(matches!(CpuSnapshot::from_bytes(&bytes), Err(SnapshotError::TrailingData)));
+    }
+
+    #[test]
+    fn history_evicts_the_oldest_snapshot_once_capacity_is_exceeded() {
+        let mut history = History::new(2);
+
+        let mut first = Cpu::default();
+        first.nodes[0].acc = 1;
+        history.push(CpuSnapshot::capture(&first));
+
+        let mut second = Cpu::default();
+        second.nodes[0].acc = 2;
+        history.push(CpuSnapshot::capture(&second));
+
+        let mut third = Cpu::default();
+        third.nodes[0].acc = 3;
+        history.push(CpuSnapshot::capture(&third));
+
+        assert_eq!(history.pop().unwrap().nodes[0].acc, 3);
+        assert_eq!(history.pop().unwrap().nodes[0].acc, 2);
+        assert!(history.pop().is_none());
+    }
+
+    #[test]
+    fn stepping_backward_restores_the_state_from_before_the_step() {
+        let mut cpu = Cpu::default();
+        cpu.nodes[0].opcodes = vec![Opcode::Add(Value::Number(1))];
+        let mut history = History::new(HISTORY_CAPACITY);
+
+        history.push(CpuSnapshot::capture(&cpu));
+        cpu.step();
+        assert_eq!(cpu.nodes[0].acc, 1);
+
+        history.pop().unwrap().restore(&mut cpu);
+        assert_eq!(cpu.nodes[0].acc, 0);
+    }
+
+    #[test]
+    fn puzzle_run_passes_when_output_matches_and_reports_instructions_executed() {
+        let programs = parse_program(PUZZLE_PROGRAM).unwrap();
+        let mut cpu = Cpu::default();
+        for (node, program) in cpu.nodes.iter_mut().zip(programs) {
+            node.opcodes = program.opcodes;
+            node.labels = program.labels;
+        }
+
+        let inputs = HashMap::from([(0, vec![1, 2, 3])]);
+        let expected_outputs = HashMap::from([(3, vec![1, 2, 3])]);
+        let report = Puzzle::new(inputs, expected_outputs).run(&mut cpu, 1000);
+
+        assert!(report.passed);
+        assert!(report.instructions > 0);
+    }
+
+    #[test]
+    fn puzzle_run_fails_when_max_cycles_is_exhausted_before_the_output_completes() {
+        let programs = parse_program(PUZZLE_PROGRAM).unwrap();
+        let mut cpu = Cpu::default();
+        for (node, program) in cpu.nodes.iter_mut().zip(programs) {
+            node.opcodes = program.opcodes;
+            node.labels = program.labels;
+        }
+
+        let inputs = HashMap::from([(0, vec![1, 2, 3])]);
+        let expected_outputs = HashMap::from([(3, vec![1, 2, 3])]);
+        let report = Puzzle::new(inputs, expected_outputs).run(&mut cpu, 1);
+
+        assert!(!report.passed);
+        assert_eq!(report.cycles, 1);
+    }
+}